@@ -0,0 +1,452 @@
+use crate::extract::{sanitized_type_ident, CaseAttr, FixtureFn, InstArguments, TestFn, Tests};
+
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Error, GenericArgument, GenericParam, Generics, Ident};
+
+pub fn generate(tests: &Tests, inst_args: &InstArguments) -> syn::Result<TokenStream> {
+    if let Some(bindings) = inst_args.named_bindings() {
+        let name = named_submodule_ident(bindings);
+        return generate_submodule(&name, tests, &Resolver::Named(bindings));
+    }
+    let combos = inst_args.combinations();
+    let names = inst_args.submodule_idents();
+    let mut out = TokenStream::new();
+    for (combo, name) in combos.iter().zip(names) {
+        out.extend(generate_submodule(&name, tests, &Resolver::Positional(combo))?);
+    }
+    Ok(out)
+}
+
+// Resolves a generic parameter to its concrete argument, by position (matrix
+// style) or by name (`name = type` binding style).
+enum Resolver<'a> {
+    Positional(&'a [GenericArgument]),
+    Named(&'a HashMap<String, GenericArgument>),
+}
+
+fn named_submodule_ident(bindings: &HashMap<String, GenericArgument>) -> Ident {
+    let mut entries: Vec<_> = bindings.iter().collect();
+    entries.sort_by_key(|(name, _)| *name);
+    let name = entries
+        .into_iter()
+        .map(|(name, arg)| format!("{name}_{}", sanitized_type_ident(arg)))
+        .collect::<Vec<_>>()
+        .join("_");
+    Ident::new(&name, proc_macro2::Span::call_site())
+}
+
+fn generate_submodule<'a>(
+    name: &Ident,
+    tests: &'a Tests,
+    resolver: &Resolver,
+) -> syn::Result<TokenStream> {
+    let mut test_fns = TokenStream::new();
+    // Fixtures whose `#[once]` cache must be shared by every test in this
+    // submodule, not rebuilt per wrapper fn. Collected in first-use order.
+    let mut once_fixtures: Vec<&'a FixtureFn> = Vec::new();
+    for test_fn in &tests.test_fns {
+        test_fns.extend(generate_test_fn(test_fn, tests, resolver, &mut once_fixtures)?);
+    }
+    let mut once_fns = TokenStream::new();
+    for fixture in &once_fixtures {
+        once_fns.extend(generate_once_accessor(fixture, resolver)?);
+    }
+    Ok(quote! {
+        mod #name {
+            use super::*;
+            #once_fns
+            #test_fns
+        }
+    })
+}
+
+fn generate_test_fn<'a>(
+    test_fn: &TestFn,
+    tests: &'a Tests,
+    resolver: &Resolver,
+    once_fixtures: &mut Vec<&'a FixtureFn>,
+) -> syn::Result<TokenStream> {
+    let mut fixture_lets = TokenStream::new();
+    let mut call_args = Vec::new();
+    for param in &test_fn.params {
+        let param_ident = fixture_param_ident(param)?;
+        let fixture = tests
+            .fixture_fns
+            .iter()
+            .find(|f| f.ident == param_ident)
+            .ok_or_else(|| {
+                Error::new_spanned(
+                    param,
+                    format!("no `#[fixture]` function named `{param_ident}` in this module"),
+                )
+            })?;
+        if fixture.once && !once_fixtures.iter().any(|f| f.ident == fixture.ident) {
+            once_fixtures.push(fixture);
+        }
+        fixture_lets.extend(generate_fixture_let(fixture, &param_ident, resolver)?);
+        call_args.push(param_ident);
+    }
+
+    if test_fn.cases.is_empty() {
+        let turbofish = turbofish_for(&test_fn.sig.generics, resolver, &test_fn.ident, None)?;
+        return generate_wrapper_fn(
+            test_fn,
+            test_fn.ident.clone(),
+            turbofish,
+            &fixture_lets,
+            &call_args,
+        );
+    }
+
+    let mut out = TokenStream::new();
+    for (i, case) in test_fn.cases.iter().enumerate() {
+        let turbofish = turbofish_for(
+            &test_fn.sig.generics,
+            resolver,
+            &test_fn.ident,
+            Some(case),
+        )?;
+        let case_ident = format_ident!("{}_case_{}", test_fn.ident, i + 1);
+        out.extend(generate_wrapper_fn(
+            test_fn,
+            case_ident,
+            turbofish,
+            &fixture_lets,
+            &call_args,
+        )?);
+    }
+    Ok(out)
+}
+
+fn generate_wrapper_fn(
+    test_fn: &TestFn,
+    wrapper_ident: Ident,
+    turbofish: TokenStream,
+    fixture_lets: &TokenStream,
+    call_args: &[Ident],
+) -> syn::Result<TokenStream> {
+    let ident = &test_fn.ident;
+    let attrs = &test_fn.test_attrs;
+    let asyncness = &test_fn.asyncness;
+    let unsafety = &test_fn.unsafety;
+    let call = quote! { super::#ident #turbofish (#(#call_args),*) };
+    let body = if test_fn.asyncness.is_some() {
+        quote! { #call.await }
+    } else {
+        call
+    };
+    Ok(quote! {
+        #(#attrs)*
+        #unsafety #asyncness fn #wrapper_ident() {
+            #fixture_lets
+            #body;
+        }
+    })
+}
+
+// Each const param takes its value from `case` (in declaration order) if one
+// is given; otherwise every non-lifetime param is resolved through `resolver`.
+fn turbofish_for(
+    generics: &Generics,
+    resolver: &Resolver,
+    fn_ident: &Ident,
+    case: Option<&CaseAttr>,
+) -> syn::Result<TokenStream> {
+    let mut positional = match resolver {
+        Resolver::Positional(combo) => combo.iter(),
+        Resolver::Named(_) => [].iter(),
+    };
+    let mut case_exprs = case.map(|c| c.exprs.iter());
+
+    let mut args = Vec::new();
+    for param in &generics.params {
+        match param {
+            GenericParam::Lifetime(_) => continue,
+            GenericParam::Const(_) if case_exprs.is_some() => {
+                let expr = case_exprs.as_mut().unwrap().next().ok_or_else(|| {
+                    Error::new_spanned(
+                        param,
+                        "not enough `#[case(...)]` values for this const generic parameter",
+                    )
+                })?;
+                args.push(quote! { { #expr } });
+            }
+            GenericParam::Type(_) | GenericParam::Const(_) => match resolver {
+                Resolver::Positional(_) => {
+                    let arg = positional.next().ok_or_else(|| {
+                        Error::new_spanned(
+                            param,
+                            "not enough `#[instantiate_tests<...>]` arguments for this test \
+                            function's generic parameters",
+                        )
+                    })?;
+                    args.push(quote! { #arg });
+                }
+                Resolver::Named(bindings) => {
+                    let name = match param {
+                        GenericParam::Type(t) => t.ident.to_string(),
+                        GenericParam::Const(c) => c.ident.to_string(),
+                        GenericParam::Lifetime(_) => unreachable!(),
+                    };
+                    let arg = bindings.get(&name).ok_or_else(|| {
+                        Error::new_spanned(
+                            param,
+                            format!(
+                                "no `{name} = ...` binding in `#[instantiate_tests<...>]` \
+                                for test function `{fn_ident}`"
+                            ),
+                        )
+                    })?;
+                    args.push(quote! { #arg });
+                }
+            },
+        }
+    }
+    Ok(quote! { ::<#(#args),*> })
+}
+
+fn fixture_param_ident(param: &syn::FnArg) -> syn::Result<Ident> {
+    let pat_type = match param {
+        syn::FnArg::Typed(pat_type) => pat_type,
+        syn::FnArg::Receiver(_) => {
+            return Err(Error::new_spanned(
+                param,
+                "`self` isn't a valid parameter on a generic test function",
+            ))
+        }
+    };
+    match &*pat_type.pat {
+        syn::Pat::Ident(pat_ident) => Ok(pat_ident.ident.clone()),
+        _ => Err(Error::new_spanned(
+            param,
+            "a test function parameter must be a plain identifier naming a `#[fixture]`",
+        )),
+    }
+}
+
+fn once_accessor_ident(fixture_ident: &Ident) -> Ident {
+    format_ident!("__{}_once", fixture_ident)
+}
+
+fn generate_fixture_let(
+    fixture: &FixtureFn,
+    param_ident: &Ident,
+    resolver: &Resolver,
+) -> syn::Result<TokenStream> {
+    if fixture.once {
+        let accessor = once_accessor_ident(&fixture.ident);
+        let call = quote! { #accessor() };
+        let call = if fixture.asyncness.is_some() {
+            quote! { #call.await }
+        } else {
+            call
+        };
+        return Ok(quote! { let #param_ident = #call; });
+    }
+    let fixture_ident = &fixture.ident;
+    let turbofish = turbofish_for(&fixture.generics, resolver, fixture_ident, None)?;
+    let call = quote! { super::#fixture_ident #turbofish () };
+    let call = if fixture.asyncness.is_some() {
+        quote! { #call.await }
+    } else {
+        call
+    };
+    Ok(quote! { let #param_ident = #call; })
+}
+
+// One `fn`/`async fn` per `#[once]` fixture, emitted once per submodule and
+// shared by every test that uses it. The `.await` (when the fixture is async)
+// happens directly in the fn body, never inside the `get_or_init` closure,
+// which must stay synchronous.
+fn generate_once_accessor(fixture: &FixtureFn, resolver: &Resolver) -> syn::Result<TokenStream> {
+    let fixture_ident = &fixture.ident;
+    let accessor_ident = once_accessor_ident(fixture_ident);
+    let turbofish = turbofish_for(&fixture.generics, resolver, fixture_ident, None)?;
+    let call = quote! { super::#fixture_ident #turbofish () };
+    let output = match &fixture.output {
+        syn::ReturnType::Type(_, ty) => quote! { #ty },
+        syn::ReturnType::Default => quote! { () },
+    };
+    if fixture.asyncness.is_some() {
+        Ok(quote! {
+            async fn #accessor_ident() -> &'static #output {
+                static CELL: std::sync::OnceLock<#output> = std::sync::OnceLock::new();
+                match CELL.get() {
+                    Some(v) => v,
+                    None => {
+                        let v = #call.await;
+                        CELL.get_or_init(|| v)
+                    }
+                }
+            }
+        })
+    } else {
+        Ok(quote! {
+            fn #accessor_ident() -> &'static #output {
+                static CELL: std::sync::OnceLock<#output> = std::sync::OnceLock::new();
+                match CELL.get() {
+                    Some(v) => v,
+                    None => {
+                        let v = #call;
+                        CELL.get_or_init(|| v)
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generics(s: &str) -> Generics {
+        syn::parse_str(s).unwrap()
+    }
+
+    fn arg(s: &str) -> GenericArgument {
+        syn::parse_str(s).unwrap()
+    }
+
+    fn fixture_fn(once: bool, asyncness: bool) -> FixtureFn {
+        FixtureFn {
+            ident: format_ident!("db"),
+            generics: generics("<>"),
+            output: syn::parse_str("-> Database").unwrap(),
+            asyncness: if asyncness {
+                Some(Default::default())
+            } else {
+                None
+            },
+            once,
+        }
+    }
+
+    #[test]
+    fn positional_turbofish_takes_prefix_in_order() {
+        let combo = [arg("u8"), arg("u16")];
+        let ts = turbofish_for(
+            &generics("<T, U>"),
+            &Resolver::Positional(&combo),
+            &format_ident!("f"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(ts.to_string(), ":: < u8 , u16 >");
+    }
+
+    #[test]
+    fn positional_turbofish_errors_on_too_few_args() {
+        let combo = [arg("u8")];
+        let err = turbofish_for(
+            &generics("<T, U>"),
+            &Resolver::Positional(&combo),
+            &format_ident!("f"),
+            None,
+        );
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn named_turbofish_resolves_by_parameter_name() {
+        let mut bindings = HashMap::new();
+        bindings.insert("U".to_string(), arg("String"));
+        bindings.insert("T".to_string(), arg("u32"));
+        let ts = turbofish_for(
+            &generics("<T, U>"),
+            &Resolver::Named(&bindings),
+            &format_ident!("f"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(ts.to_string(), ":: < u32 , String >");
+    }
+
+    #[test]
+    fn named_turbofish_errors_on_missing_binding() {
+        let bindings = HashMap::new();
+        let err = turbofish_for(
+            &generics("<T>"),
+            &Resolver::Named(&bindings),
+            &format_ident!("f"),
+            None,
+        );
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn case_turbofish_fills_const_params_from_case_exprs() {
+        let case = CaseAttr {
+            exprs: vec![syn::parse_str("3").unwrap()],
+        };
+        let combo = [arg("u8")];
+        let ts = turbofish_for(
+            &generics("<T, const N: usize>"),
+            &Resolver::Positional(&combo),
+            &format_ident!("f"),
+            Some(&case),
+        )
+        .unwrap();
+        assert_eq!(ts.to_string(), ":: < u8 , { 3 } >");
+    }
+
+    #[test]
+    fn named_submodule_ident_is_sorted_and_deterministic() {
+        let mut bindings = HashMap::new();
+        bindings.insert("U".to_string(), arg("String"));
+        bindings.insert("T".to_string(), arg("u32"));
+        assert_eq!(named_submodule_ident(&bindings).to_string(), "T_u32_U_String");
+    }
+
+    #[test]
+    fn once_fixture_let_calls_shared_accessor_without_rebuilding() {
+        let fixture = fixture_fn(true, false);
+        let ts = generate_fixture_let(&fixture, &format_ident!("db"), &Resolver::Positional(&[])).unwrap();
+        assert_eq!(ts.to_string(), "let db = __db_once () ;");
+    }
+
+    #[test]
+    fn once_async_accessor_awaits_outside_the_cache_closure() {
+        let fixture = fixture_fn(true, true);
+        let ts = generate_once_accessor(&fixture, &Resolver::Positional(&[])).unwrap();
+        let rendered = ts.to_string();
+        assert!(rendered.starts_with("async fn __db_once"));
+        // The `.await` must land on the call that produces `v`, not inside
+        // the synchronous closure passed to `get_or_init`.
+        assert!(rendered.contains("let v = super :: db :: < > () . await ;"));
+        assert!(!rendered.contains("get_or_init (|| super"));
+    }
+
+    #[test]
+    fn once_accessor_is_emitted_once_per_submodule_even_with_two_callers() {
+        let fixture_fns = vec![fixture_fn(true, false)];
+        let param: syn::FnArg = syn::parse_str("db: Database").unwrap();
+        let test_attrs: syn::ItemFn = syn::parse_str("#[test] fn f() {}").unwrap();
+        let test_fn = |ident: &str| TestFn {
+            test_attrs: test_attrs.attrs.clone(),
+            asyncness: None,
+            unsafety: None,
+            ident: format_ident!("{}", ident),
+            output: syn::ReturnType::Default,
+            sig: crate::signature::TestFnSignature {
+                ident: format_ident!("{}", ident),
+                generics: generics("<>"),
+            },
+            params: vec![param.clone()],
+            cases: Vec::new(),
+        };
+        let tests = Tests {
+            test_fns: vec![test_fn("a"), test_fn("b")],
+            fixture_fns,
+        };
+        let name = format_ident!("instantiated");
+        let ts = generate_submodule(&name, &tests, &Resolver::Positional(&[])).unwrap();
+        let rendered = ts.to_string();
+        assert_eq!(rendered.matches("static CELL").count(), 1);
+        assert_eq!(rendered.matches("fn __db_once").count(), 1);
+        assert_eq!(rendered.matches("let db = __db_once () ;").count(), 2);
+    }
+}
@@ -0,0 +1,24 @@
+use syn::Error;
+
+#[derive(Default)]
+pub struct ErrorRecord {
+    errors: Vec<Error>,
+}
+
+impl ErrorRecord {
+    pub fn add_error(&mut self, error: Error) {
+        self.errors.push(error);
+    }
+
+    pub fn check(self) -> syn::Result<()> {
+        let mut errors = self.errors.into_iter();
+        let mut combined = match errors.next() {
+            Some(first) => first,
+            None => return Ok(()),
+        };
+        for error in errors {
+            combined.combine(error);
+        }
+        Err(combined)
+    }
+}
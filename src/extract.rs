@@ -2,18 +2,19 @@ use crate::error::ErrorRecord;
 use crate::options::MacroOpts;
 use crate::signature::TestFnSignature;
 
-use proc_macro2::TokenStream;
 use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
 use syn::punctuated::Punctuated;
 use syn::Token;
 use syn::{
-    AngleBracketedGenericArguments, AttrStyle, Attribute, Error, GenericArgument, GenericParam,
-    Generics, Ident, Item, ItemFn, ItemMod, ReturnType,
+    AttrStyle, Attribute, Error, GenericArgument, GenericParam, Generics, Ident, Item, ItemFn,
+    ItemMod, ReturnType,
 };
 
 #[derive(Default)]
 pub struct Tests {
     pub test_fns: Vec<TestFn>,
+    pub fixture_fns: Vec<FixtureFn>,
 }
 
 pub struct TestFn {
@@ -23,32 +24,62 @@ pub struct TestFn {
     pub ident: Ident,
     pub output: ReturnType,
     pub sig: TestFnSignature,
+    // Resolved against `Tests::fixture_fns` by the emitter, not part of the public test signature.
+    pub params: Vec<syn::FnArg>,
+    pub cases: Vec<CaseAttr>,
+}
+
+pub struct CaseAttr {
+    pub exprs: Vec<syn::Expr>,
+}
+
+pub struct FixtureFn {
+    pub ident: Ident,
+    pub generics: Generics,
+    pub output: ReturnType,
+    pub asyncness: Option<Token![async]>,
+    pub once: bool,
 }
 
 impl Tests {
     pub fn try_extract<'ast>(
         opts: &MacroOpts,
         ast: &'ast mut ItemMod,
-    ) -> syn::Result<(Self, &'ast mut Vec<Item>)> {
+    ) -> syn::Result<(Self, Option<InstArguments>, &'ast mut Vec<Item>)> {
         if ast.content.is_none() {
             return Err(Error::new_spanned(ast, "only inline modules are supported"));
         }
+        let inst_args = InstArguments::try_extract(ast)?;
         let items = &mut ast.content.as_mut().unwrap().1;
-        let (tests, errors) = Self::extract_recording_errors(opts, items);
+        let (tests, errors) = Self::extract_recording_errors(opts, items, inst_args.as_ref());
         errors.check()?;
-        Ok((tests, items))
+        Ok((tests, inst_args, items))
     }
 
     fn extract_recording_errors<'ast>(
         opts: &MacroOpts,
         items: &'ast mut Vec<Item>,
+        inst_args: Option<&InstArguments>,
     ) -> (Self, ErrorRecord) {
         let mut errors = ErrorRecord::default();
         let mut tests = Tests::default();
         let mut mod_wide_generic_arity = None;
+        let named_bindings = inst_args.and_then(InstArguments::named_bindings);
         for item in items.iter_mut() {
             if let Item::Fn(item) = item {
+                match extract_fixture_fn(opts, item) {
+                    Ok(Some(fixture_fn)) => {
+                        tests.fixture_fns.push(fixture_fn);
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        errors.add_error(e);
+                        continue;
+                    }
+                }
                 if let Some(test_attrs) = extract_test_attrs(opts, item) {
+                    let params: Vec<syn::FnArg> = item.sig.inputs.iter().cloned().collect();
                     let sig = match TestFnSignature::try_build(item) {
                         Ok(sig) => sig,
                         Err(e) => {
@@ -56,22 +87,95 @@ impl Tests {
                             continue;
                         }
                     };
-                    let fn_generic_arity = generic_arity(&item.sig.generics);
-                    match mod_wide_generic_arity {
-                        None => {
-                            mod_wide_generic_arity = Some(fn_generic_arity);
+                    let cases = match extract_case_attrs(item) {
+                        Ok(cases) => cases,
+                        Err(e) => {
+                            errors.add_error(e);
+                            continue;
+                        }
+                    };
+                    if !cases.is_empty() {
+                        let const_param_count = item
+                            .sig
+                            .generics
+                            .params
+                            .iter()
+                            .filter(|p| matches!(p, GenericParam::Const(_)))
+                            .count();
+                        if const_param_count == 0 {
+                            errors.add_error(Error::new_spanned(
+                                &item.sig.generics,
+                                format!(
+                                    "`#[case(...)]` on `{}` but it has no const generic parameter",
+                                    item.sig.ident
+                                ),
+                            ));
+                            continue;
                         }
-                        Some(n) => {
-                            if fn_generic_arity != n {
+                        if let Some(bad) = cases.iter().find(|c| c.exprs.len() != const_param_count)
+                        {
+                            errors.add_error(Error::new_spanned(
+                                &item.sig.generics,
+                                format!(
+                                    "`#[case(...)]` on `{}` supplies {} value(s) but it has {} \
+                                    const generic parameter(s)",
+                                    item.sig.ident,
+                                    bad.exprs.len(),
+                                    const_param_count
+                                ),
+                            ));
+                            continue;
+                        }
+                    }
+                    if let Some(bindings) = named_bindings {
+                        // Named bindings let each test function pull only
+                        // the subset it declares, so the mod-wide uniform
+                        // arity check below doesn't apply; instead every
+                        // generic parameter the function names must be bound.
+                        let mut ok = true;
+                        for param in &item.sig.generics.params {
+                            let name = match param {
+                                GenericParam::Type(t) => &t.ident,
+                                // Const params covered by #[case(...)] are
+                                // filled in per-case, not by a named binding.
+                                GenericParam::Const(_) if !cases.is_empty() => continue,
+                                GenericParam::Const(c) => &c.ident,
+                                GenericParam::Lifetime(_) => continue,
+                            };
+                            if !bindings.contains_key(&name.to_string()) {
                                 errors.add_error(Error::new_spanned(
-                                    &item.sig.generics,
+                                    param,
                                     format!(
-                                        "test function `{}` has {} generic parameters \
-                                        while others in the same module have {}",
-                                        item.sig.ident, fn_generic_arity, n
+                                        "no `{name} = ...` binding in `#[instantiate_tests<...>]` \
+                                        for test function `{}`",
+                                        item.sig.ident
                                     ),
                                 ));
-                                continue;
+                                ok = false;
+                            }
+                        }
+                        if !ok {
+                            continue;
+                        }
+                    } else {
+                        let fn_generic_arity =
+                            generic_arity(&item.sig.generics, !cases.is_empty());
+                        match mod_wide_generic_arity {
+                            None => {
+                                mod_wide_generic_arity = Some(fn_generic_arity);
+                            }
+                            Some(n) => {
+                                if fn_generic_arity != n {
+                                    errors.add_error(Error::new_spanned(
+                                        &item.sig.generics,
+                                        format!(
+                                            "test function `{}` has {} generic parameters \
+                                            while others in the same module have {}",
+                                            item.sig.ident, fn_generic_arity, n
+                                        ),
+                                    ));
+                                    continue;
+                                }
                             }
                         }
                     }
@@ -82,10 +186,28 @@ impl Tests {
                         ident: item.sig.ident.clone(),
                         output: item.sig.output.clone(),
                         sig,
+                        params,
+                        cases,
                     });
                 }
             }
         }
+        // Named bindings are resolved per test function above and never
+        // populate `mod_wide_generic_arity`, so this only fires for the
+        // positional (matrix) style.
+        if let (Some(n), Some(ia)) = (mod_wide_generic_arity, inst_args) {
+            if ia.named_bindings().is_none() && ia.arity() != n {
+                errors.add_error(Error::new(
+                    proc_macro2::Span::call_site(),
+                    format!(
+                        "`#[instantiate_tests<...>]` supplies {} generic argument(s) but test \
+                        functions in this module have {}",
+                        ia.arity(),
+                        n
+                    ),
+                ));
+            }
+        }
         (tests, errors)
     }
 }
@@ -113,18 +235,166 @@ fn extract_test_attrs(opts: &MacroOpts, item: &mut ItemFn) -> Option<Vec<Attribu
     }
 }
 
-fn generic_arity(generics: &Generics) -> usize {
+fn extract_fixture_fn(opts: &MacroOpts, item: &mut ItemFn) -> syn::Result<Option<FixtureFn>> {
+    let mut is_fixture = false;
+    let mut once_attr = None;
+    let mut pos = 0;
+    while pos < item.attrs.len() {
+        let attr = &item.attrs[pos];
+        if opts.is_fixture_attr(attr) {
+            is_fixture = true;
+            item.attrs.remove(pos);
+            continue;
+        }
+        if attr.path.is_ident("once") {
+            once_attr = Some(item.attrs.remove(pos));
+            continue;
+        }
+        pos += 1;
+    }
+    if !is_fixture {
+        return match once_attr {
+            Some(once_attr) => Err(Error::new_spanned(
+                once_attr,
+                "`#[once]` can only be used on a `#[fixture]` function",
+            )),
+            None => Ok(None),
+        };
+    }
+    Ok(Some(FixtureFn {
+        ident: item.sig.ident.clone(),
+        generics: item.sig.generics.clone(),
+        output: item.sig.output.clone(),
+        asyncness: item.sig.asyncness,
+        once: once_attr.is_some(),
+    }))
+}
+
+// Const generic parameters covered by `#[case(...)]` are filled in per-case,
+// not by `#[instantiate_tests<...>]`, so they're excluded here.
+fn generic_arity(generics: &Generics, has_cases: bool) -> usize {
     generics
         .params
         .iter()
         .filter(|param| match param {
-            GenericParam::Type(_) | GenericParam::Const(_) => true,
+            GenericParam::Type(_) => true,
+            GenericParam::Const(_) => !has_cases,
             GenericParam::Lifetime(_) => false,
         })
         .count()
 }
 
-pub struct InstArguments(Punctuated<GenericArgument, Token![,]>);
+fn extract_case_attrs(item: &mut ItemFn) -> syn::Result<Vec<CaseAttr>> {
+    let mut cases = Vec::new();
+    let mut pos = 0;
+    while pos < item.attrs.len() {
+        if item.attrs[pos].path.is_ident("case") {
+            let attr = item.attrs.remove(pos);
+            let exprs: Punctuated<syn::Expr, Token![,]> =
+                attr.parse_args_with(Punctuated::parse_terminated)?;
+            cases.push(CaseAttr {
+                exprs: exprs.into_iter().collect(),
+            });
+            continue;
+        }
+        pos += 1;
+    }
+    Ok(cases)
+}
+
+// One entry inside `#[instantiate_tests<...>]`: a positional axis (possibly a
+// bracketed list of alternatives to cross matrix-style), or a `name = type`
+// binding. A single attribute must use one style throughout.
+//
+// `Named`'s argument is boxed because `GenericArgument` is much larger than
+// `Positional`'s `Vec`, and entries are held by value in a `Punctuated` while
+// `InstArguments` is parsed.
+enum InstEntry {
+    Positional(Vec<GenericArgument>),
+    Named(Ident, Box<GenericArgument>),
+}
+
+impl Parse for InstEntry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Ident) && input.peek2(Token![=]) {
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let arg: GenericArgument = input.parse()?;
+            return Ok(InstEntry::Named(name, Box::new(arg)));
+        }
+        if input.peek(syn::token::Bracket) {
+            let content;
+            syn::bracketed!(content in input);
+            let args: Punctuated<GenericArgument, Token![,]> =
+                content.parse_terminated(GenericArgument::parse)?;
+            if args.is_empty() {
+                return Err(Error::new(
+                    content.span(),
+                    "matrix list `[...]` must not be empty",
+                ));
+            }
+            return Ok(InstEntry::Positional(args.into_iter().collect()));
+        }
+        Ok(InstEntry::Positional(vec![input.parse()?]))
+    }
+}
+
+// Positional axes (one per generic parameter position, each possibly a matrix
+// of alternatives), or `name = type` bindings. Mutually exclusive styles.
+pub enum InstArguments {
+    Positional(Vec<Vec<GenericArgument>>),
+    Named(std::collections::HashMap<String, GenericArgument>),
+}
+
+impl Parse for InstArguments {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![<]>()?;
+        let mut entries = Punctuated::<InstEntry, Token![,]>::new();
+        while !input.peek(Token![>]) {
+            entries.push_value(input.parse::<InstEntry>()?);
+            if input.peek(Token![>]) {
+                break;
+            }
+            entries.push_punct(input.parse::<Token![,]>()?);
+        }
+        input.parse::<Token![>]>()?;
+
+        let is_named = matches!(entries.iter().next(), Some(InstEntry::Named(..)));
+        if is_named {
+            let mut bindings = std::collections::HashMap::new();
+            for entry in entries {
+                match entry {
+                    InstEntry::Named(name, arg) => {
+                        bindings.insert(name.to_string(), *arg);
+                    }
+                    InstEntry::Positional(_) => {
+                        return Err(Error::new(
+                            input.span(),
+                            "cannot mix `name = type` bindings with positional arguments \
+                            in the same `#[instantiate_tests<...>]`",
+                        ));
+                    }
+                }
+            }
+            Ok(InstArguments::Named(bindings))
+        } else {
+            let mut axes = Vec::new();
+            for entry in entries {
+                match entry {
+                    InstEntry::Positional(args) => axes.push(args),
+                    InstEntry::Named(name, _) => {
+                        return Err(Error::new_spanned(
+                            name,
+                            "cannot mix `name = type` bindings with positional arguments \
+                            in the same `#[instantiate_tests<...>]`",
+                        ));
+                    }
+                }
+            }
+            Ok(InstArguments::Positional(axes))
+        }
+    }
+}
 
 impl InstArguments {
     pub fn try_extract(item: &mut ItemMod) -> syn::Result<Option<Self>> {
@@ -136,17 +406,237 @@ impl InstArguments {
                         return Err(Error::new_spanned(attr, "cannot be an inner attribute"))
                     }
                 };
-                let AngleBracketedGenericArguments { args, .. } = attr.parse_args()?;
+                let inst_args: InstArguments = attr.parse_args()?;
                 item.attrs.remove(pos);
-                return Ok(Some(InstArguments(args)));
+                return Ok(Some(inst_args));
             }
         }
         Ok(None)
     }
+
+    pub fn named_bindings(&self) -> Option<&std::collections::HashMap<String, GenericArgument>> {
+        match self {
+            InstArguments::Named(bindings) => Some(bindings),
+            InstArguments::Positional(_) => None,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self {
+            InstArguments::Positional(axes) => axes.len(),
+            InstArguments::Named(bindings) => bindings.len(),
+        }
+    }
+
+    // Odometer order: the rightmost axis varies fastest. Empty for named bindings.
+    pub fn combinations(&self) -> Vec<Vec<GenericArgument>> {
+        let axes = match self {
+            InstArguments::Positional(axes) => axes,
+            InstArguments::Named(_) => return Vec::new(),
+        };
+        let mut combos: Vec<Vec<GenericArgument>> = vec![Vec::new()];
+        for axis in axes {
+            let mut next = Vec::with_capacity(combos.len() * axis.len());
+            for combo in &combos {
+                for arg in axis {
+                    let mut combo = combo.clone();
+                    combo.push(arg.clone());
+                    next.push(combo);
+                }
+            }
+            combos = next;
+        }
+        combos
+    }
+
+    // Same order as `combinations`, disambiguated against every name already
+    // emitted (not just the raw base), so a later combo's base can't collide
+    // with an earlier combo's own disambiguated name.
+    pub fn submodule_idents(&self) -> Vec<Ident> {
+        let mut seen = std::collections::HashSet::<String>::new();
+        self.combinations()
+            .iter()
+            .map(|combo| {
+                let base = combo
+                    .iter()
+                    .map(sanitized_type_ident)
+                    .collect::<Vec<_>>()
+                    .join("_");
+                // A module with no generic test functions has no axes, so
+                // its sole combo is empty; fall back to a non-empty name
+                // rather than handing `Ident::new` an empty string.
+                let base = if base.is_empty() {
+                    "instantiated".to_string()
+                } else {
+                    base
+                };
+                let mut name = base.clone();
+                let mut suffix = 0;
+                while !seen.insert(name.clone()) {
+                    suffix += 1;
+                    name = format!("{}_{}", base, suffix);
+                }
+                Ident::new(&name, proc_macro2::Span::call_site())
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn sanitized_type_ident(arg: &GenericArgument) -> String {
+    let raw = arg.to_token_stream().to_string();
+    let mut out = String::with_capacity(raw.len());
+    let mut prev_underscore = false;
+    for c in raw.chars() {
+        match c {
+            '<' | '>' | ':' | ' ' | '\t' | '\n' => {
+                if !prev_underscore && !out.is_empty() {
+                    out.push('_');
+                    prev_underscore = true;
+                }
+            }
+            c => {
+                out.push(c);
+                prev_underscore = false;
+            }
+        }
+    }
+    while out.ends_with('_') {
+        out.pop();
+    }
+    out
 }
 
-impl ToTokens for InstArguments {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        self.0.to_tokens(tokens)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_inst_args(s: &str) -> syn::Result<InstArguments> {
+        syn::parse_str(s)
+    }
+
+    #[test]
+    fn positional_single_arg_per_axis() {
+        let args = parse_inst_args("<u32, String>").unwrap();
+        assert_eq!(args.arity(), 2);
+        assert_eq!(args.combinations().len(), 1);
+    }
+
+    #[test]
+    fn matrix_list_crosses_axes() {
+        let args = parse_inst_args("<[u8, u16], [u32]>").unwrap();
+        assert_eq!(args.arity(), 2);
+        let combos = args.combinations();
+        assert_eq!(combos.len(), 2);
+        let names: Vec<String> = args
+            .submodule_idents()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(names, vec!["u8_u32", "u16_u32"]);
+    }
+
+    #[test]
+    fn empty_matrix_list_is_rejected() {
+        assert!(parse_inst_args("<[]>").is_err());
+    }
+
+    #[test]
+    fn named_bindings_parse() {
+        let args = parse_inst_args("<T = u32, U = String>").unwrap();
+        let bindings = args.named_bindings().unwrap();
+        assert_eq!(bindings.len(), 2);
+        assert!(bindings.contains_key("T"));
+        assert!(bindings.contains_key("U"));
+    }
+
+    #[test]
+    fn mixing_named_and_positional_is_rejected() {
+        assert!(parse_inst_args("<T = u32, String>").is_err());
+        assert!(parse_inst_args("<u32, T = String>").is_err());
+    }
+
+    #[test]
+    fn submodule_idents_disambiguate_collisions() {
+        let args = parse_inst_args("<[u8, u8]>").unwrap();
+        let names: Vec<String> = args
+            .submodule_idents()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(names, vec!["u8", "u8_1"]);
+    }
+
+    #[test]
+    fn submodule_idents_disambiguate_against_emitted_names_not_just_bases() {
+        // Bases "u8", "u8", "u8_1" in that order: the naive count-by-base
+        // approach would hand out "u8", "u8_1", "u8_1" (a collision), since
+        // it never checks that "u8_1" was already produced as a disambiguated
+        // name for the second combo.
+        let args = parse_inst_args("<[u8, u8, u8_1]>").unwrap();
+        let names: Vec<String> = args
+            .submodule_idents()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(names, vec!["u8", "u8_1", "u8_1_1"]);
+    }
+
+    #[test]
+    fn empty_instantiate_tests_is_tolerated_not_a_panic() {
+        let args = parse_inst_args("<>").unwrap();
+        assert_eq!(args.arity(), 0);
+        assert_eq!(args.combinations(), vec![Vec::new()]);
+        let names: Vec<String> = args
+            .submodule_idents()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(names, vec!["instantiated"]);
+    }
+
+    #[test]
+    fn once_without_fixture_is_an_error() {
+        let opts = MacroOpts::default();
+        let mut item: ItemFn = syn::parse_str("#[once] fn f() -> u32 { 0 }").unwrap();
+        assert!(extract_fixture_fn(&opts, &mut item).is_err());
+    }
+
+    #[test]
+    fn fixture_without_once_is_not_cached() {
+        let opts = MacroOpts::default();
+        let mut item: ItemFn = syn::parse_str("#[fixture] fn f() -> u32 { 0 }").unwrap();
+        let fixture = extract_fixture_fn(&opts, &mut item).unwrap().unwrap();
+        assert!(!fixture.once);
+    }
+
+    #[test]
+    fn fixture_with_once_is_cached() {
+        let opts = MacroOpts::default();
+        let mut item: ItemFn = syn::parse_str("#[once] #[fixture] fn f() -> u32 { 0 }").unwrap();
+        let fixture = extract_fixture_fn(&opts, &mut item).unwrap().unwrap();
+        assert!(fixture.once);
+    }
+
+    #[test]
+    fn non_fixture_function_is_left_alone() {
+        let opts = MacroOpts::default();
+        let mut item: ItemFn = syn::parse_str("fn f() {}").unwrap();
+        assert!(extract_fixture_fn(&opts, &mut item).unwrap().is_none());
+    }
+
+    #[test]
+    fn case_attrs_are_extracted_in_order() {
+        let mut item: ItemFn = syn::parse_str("#[case(1, 2)] #[case(3, 4)] fn f<const N: usize, const M: usize>() {}").unwrap();
+        let cases = extract_case_attrs(&mut item).unwrap();
+        assert_eq!(cases.len(), 2);
+        assert_eq!(cases[0].exprs.len(), 2);
+        assert!(item.attrs.is_empty());
+    }
+
+    #[test]
+    fn generic_arity_excludes_const_params_with_cases() {
+        let generics: Generics = syn::parse_str("<T, const N: usize>").unwrap();
+        assert_eq!(generic_arity(&generics, false), 2);
+        assert_eq!(generic_arity(&generics, true), 1);
     }
 }
@@ -0,0 +1,33 @@
+use syn::Attribute;
+
+pub struct MacroOpts {
+    test_attr: String,
+    copied_attrs: Vec<String>,
+    fixture_attr: String,
+}
+
+impl Default for MacroOpts {
+    fn default() -> Self {
+        MacroOpts {
+            test_attr: "test".to_string(),
+            copied_attrs: Vec::new(),
+            fixture_attr: "fixture".to_string(),
+        }
+    }
+}
+
+impl MacroOpts {
+    pub fn is_test_attr(&self, attr: &Attribute) -> bool {
+        attr.path.is_ident(&self.test_attr)
+    }
+
+    pub fn is_copied_attr(&self, attr: &Attribute) -> bool {
+        self.copied_attrs
+            .iter()
+            .any(|ident| attr.path.is_ident(ident))
+    }
+
+    pub fn is_fixture_attr(&self, attr: &Attribute) -> bool {
+        attr.path.is_ident(&self.fixture_attr)
+    }
+}
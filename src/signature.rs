@@ -0,0 +1,18 @@
+use syn::{Generics, Ident, ItemFn};
+
+// Parameters are intentionally not recorded or validated here: a test function
+// may take `#[fixture]` parameters, resolved only once all fixtures in the
+// module are collected.
+pub struct TestFnSignature {
+    pub ident: Ident,
+    pub generics: Generics,
+}
+
+impl TestFnSignature {
+    pub fn try_build(item: &ItemFn) -> syn::Result<Self> {
+        Ok(TestFnSignature {
+            ident: item.sig.ident.clone(),
+            generics: item.sig.generics.clone(),
+        })
+    }
+}